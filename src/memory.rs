@@ -1,12 +1,90 @@
-use colored::Colorize;
+use colored::{Color, Colorize};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
 
 use crate::utils::{format_size, get_cmd, parse_value};
 use crate::AnyError;
 
-#[derive(Default, Clone, Copy)]
+/// Output format for machine-readable dumps, selected via `--format`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Csv,
+}
+
+/// Serializes `value` in the given machine-readable `format`. Fields stay in
+/// bytes/kB as already parsed, never pre-formatted like [`format_size`] does,
+/// so downstream monitoring pipelines and scripts can do their own math.
+pub fn format_output<T: Serialize>(value: &T, format: OutputFormat) -> Result<String, AnyError> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(value)?),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            writer.serialize(value)?;
+            writer.flush()?;
+            Ok(String::from_utf8(writer.into_inner()?)?)
+        }
+    }
+}
+
+/// Same as [`format_output`] but for a table of rows, e.g. the process list
+/// or per-user aggregation. CSV gets one row per element under a single
+/// header; serializing the whole slice through [`format_output`] instead
+/// would flatten every element's fields onto one line.
+pub fn format_rows<T: Serialize>(values: &[T], format: OutputFormat) -> Result<String, AnyError> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(values)?),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for value in values {
+                writer.serialize(value)?;
+            }
+            writer.flush()?;
+            Ok(String::from_utf8(writer.into_inner()?)?)
+        }
+    }
+}
+
+/// Sentinel `memory.limit_in_bytes` value cgroup v1 uses to mean "unlimited".
+const CGROUP_V1_UNLIMITED: u64 = 9_223_372_036_854_771_712;
+
+/// Warning/critical percentage thresholds used to color the `used`/`available`
+/// figures in [`MemoryStats::display`] and the PSS column in
+/// [`ProcessMemoryStats::display`] and [`UserMemoryStats::display`].
+/// Defaults mirror the thresholds status-bar memory blocks commonly use.
+#[derive(Clone, Copy)]
+pub struct MemoryThresholds {
+    pub warning_pct: f64,
+    pub critical_pct: f64,
+}
+
+impl Default for MemoryThresholds {
+    fn default() -> Self {
+        Self {
+            warning_pct: 80.0,
+            critical_pct: 90.0,
+        }
+    }
+}
+
+/// Picks green/yellow/red for a usage ratio (0-100) against `thresholds`.
+fn pressure_color(used_pct: f64, thresholds: &MemoryThresholds) -> Color {
+    if used_pct >= thresholds.critical_pct {
+        Color::Red
+    } else if used_pct >= thresholds.warning_pct {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
+
+#[derive(Default, Clone, Copy, Serialize)]
 pub struct MemoryStats {
     pub total: u64,
     pub free: u64,
@@ -26,6 +104,14 @@ pub struct MemoryStats {
     pub freevmem: u64,
     pub usedvmem: u64,
     pub availablevmem: u64,
+    pub arc_size: u64,
+    pub arc_max: u64,
+    pub has_arc: bool,
+    pub cgroup: Option<CgroupMemoryStats>,
+    #[cfg(feature = "gpu")]
+    pub gpu_used: u64,
+    #[cfg(feature = "gpu")]
+    pub gpu_total: u64,
 }
 
 impl MemoryStats {
@@ -62,12 +148,68 @@ impl MemoryStats {
         }
         self.used = self.total - self.free - self.buffers - self.cached;
         self.swap_used = self.swap_total - self.swap_free;
-        self.compression_ratio = self.zswap as f64 / self.zswap_compressed as f64;
+        self.compression_ratio = if self.zswap_compressed > 0 {
+            self.zswap as f64 / self.zswap_compressed as f64
+        } else {
+            0.0
+        };
         self.totalvmem = self.total + self.swap_total;
         self.freevmem = self.free + self.swap_free;
         self.usedvmem = self.used + self.swap_used;
         self.availablevmem = self.available + self.swap_free;
 
+        self.update_arc()?;
+        self.update_cgroup()?;
+        #[cfg(feature = "gpu")]
+        self.update_gpu()?;
+
+        Ok(())
+    }
+
+    /// ### Update Cgroup
+    /// Populates container-scoped memory accounting via [`CgroupMemoryStats`]
+    /// when running under a cgroup, so `display()` can show the container's
+    /// real limits alongside the host-wide numbers from `/proc/meminfo`.
+    /// Not being in a cgroup (or lacking permission to read it) is not an
+    /// error, it just leaves `cgroup` as `None`.
+    fn update_cgroup(&mut self) -> Result<(), AnyError> {
+        let mut cgroup = CgroupMemoryStats::new();
+        if cgroup.update(self.total).is_ok() {
+            self.cgroup = Some(cgroup);
+        }
+
+        Ok(())
+    }
+
+    /// ### Update ARC
+    /// Reads the ZFS ARC's current size and cap from `/proc/spl/kstat/zfs/arcstats`,
+    /// if present. The file is a `name type value` table, already in bytes, so the
+    /// values are normalized to kB to match the rest of `MemoryStats`. Absence of
+    /// the file (no ZFS loaded) is not an error, it just leaves `has_arc` false.
+    fn update_arc(&mut self) -> Result<(), AnyError> {
+        let contents = match fs::read_to_string("/proc/spl/kstat/zfs/arcstats") {
+            Ok(contents) => contents,
+            Err(_) => return Ok(()),
+        };
+
+        for line in contents.lines() {
+            let mut split = line.split_whitespace();
+            let key = match split.next() {
+                Some(key) => key,
+                None => continue,
+            };
+            let value = match split.nth(1) {
+                Some(value) => value,
+                None => continue,
+            };
+            match key {
+                "size" => self.arc_size = value.parse::<u64>()? / 1024,
+                "c_max" => self.arc_max = value.parse::<u64>()? / 1024,
+                _ => (),
+            }
+        }
+        self.has_arc = true;
+
         Ok(())
     }
 
@@ -82,7 +224,7 @@ impl MemoryStats {
     ///            Zswap      Compressed           Ratio
     ///Zswap:    1.68 GB       764.75 MB           2.256
     /// ```
-    pub fn display(&self) {
+    pub fn display(&self, thresholds: &MemoryThresholds) {
         let fmt = |s: u64| format!("{:>15}", format_size(s));
         let fmt_mem = |s: u64| format!("{:>12}", format_size(s));
         let fmt_swap = |s: u64| format!("{:>11}", format_size(s));
@@ -90,6 +232,25 @@ impl MemoryStats {
         let fmt_zswap = |s: u64| format!("{:>10}", format_size(s));
         let fmt_ratio = |s: f64| format!("{:>15.3}", s);
 
+        let mem_pct = if self.total > 0 {
+            self.used as f64 / self.total as f64 * 100.0
+        } else {
+            0.0
+        };
+        let swap_pct = if self.swap_total > 0 {
+            self.swap_used as f64 / self.swap_total as f64 * 100.0
+        } else {
+            0.0
+        };
+        let total_pct = if self.totalvmem > 0 {
+            self.usedvmem as f64 / self.totalvmem as f64 * 100.0
+        } else {
+            0.0
+        };
+        let mem_color = pressure_color(mem_pct, thresholds);
+        let swap_color = pressure_color(swap_pct, thresholds);
+        let total_color = pressure_color(total_pct, thresholds);
+
         println!(
             "{:>17} {:>15} {:>15} {:>15} {:>15} {:>15}",
             "total".bold(),
@@ -103,49 +264,268 @@ impl MemoryStats {
             "{} {} {} {} {} {} {}",
             "Mem:".bold().cyan(),
             fmt_mem(self.total).green(),
-            fmt(self.used).red(),
+            fmt(self.used).color(mem_color),
             fmt(self.free).cyan(),
             fmt(self.shared).yellow(),
             fmt(self.buffers + self.cached).magenta(),
-            fmt(self.available).blue()
+            fmt(self.available).color(mem_color)
         );
+        if self.swap_total > 0 {
+            println!(
+                "{} {} {} {} {:>15} {} {}",
+                "Swap:".bold().purple(),
+                fmt_swap(self.swap_total).green(),
+                fmt(self.swap_used).color(swap_color),
+                fmt(self.swap_free).cyan(),
+                "",
+                fmt(self.swap_cached).yellow(),
+                fmt(self.swap_free).color(swap_color)
+            );
+            println!(
+                "{} {} {} {} {:>15} {:>15} {}",
+                "Total:".bold().blue(),
+                fmt_total(self.totalvmem).green(),
+                fmt(self.usedvmem).color(total_color),
+                fmt(self.freevmem).cyan(),
+                "",
+                "",
+                fmt(self.availablevmem).color(total_color)
+            );
+        }
+
+        if self.zswap_compressed > 0 {
+            println!(
+                "\n{:>17} {:>15} {:>15}",
+                "Zswap".bold(),
+                "Compressed".bold(),
+                "Ratio".bold()
+            );
+            println!(
+                "{} {} {} {}",
+                "Zswap:".bold().purple(),
+                fmt_zswap(self.zswap).green(),
+                fmt(self.zswap_compressed).red(),
+                fmt_ratio(self.compression_ratio).cyan()
+            );
+        }
+
+        if self.has_arc {
+            println!("\n{:>17} {:>15}", "ARC".bold(), "Max".bold());
+            println!(
+                "{} {} {}",
+                "ARC:".bold().green(),
+                fmt(self.arc_size).red(),
+                fmt(self.arc_max).blue()
+            );
+        }
+
+        if let Some(cgroup) = &self.cgroup {
+            cgroup.display();
+        }
+
+        #[cfg(feature = "gpu")]
+        {
+            println!("\n{:>17} {:>15}", "GPU used".bold(), "GPU total".bold());
+            println!(
+                "{} {} {}",
+                "GPU:".bold().green(),
+                fmt(self.gpu_used).red(),
+                fmt(self.gpu_total).blue()
+            );
+        }
+    }
+
+    /// ### Update GPU
+    /// Queries NVML for the used/total VRAM across all visible devices. Only
+    /// compiled in with the `gpu` feature, since it pulls in an NVML binding
+    /// and isn't available on machines without an NVIDIA driver.
+    #[cfg(feature = "gpu")]
+    fn update_gpu(&mut self) -> Result<(), AnyError> {
+        let nvml = nvml_wrapper::Nvml::init()?;
+        let (mut used, mut total) = (0, 0);
+        for i in 0..nvml.device_count()? {
+            let info = nvml.device_by_index(i)?.memory_info()?;
+            used += info.used / 1024;
+            total += info.total / 1024;
+        }
+        self.gpu_used = used;
+        self.gpu_total = total;
+
+        Ok(())
+    }
+}
+
+/// Which cgroup version (if any) backs a process's memory accounting.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CgroupVersion {
+    #[default]
+    None,
+    V1,
+    V2,
+}
+
+/// Container-scoped memory accounting, read straight from the cgroup
+/// hierarchy instead of `/proc/meminfo`. Inside a container `/proc/meminfo`
+/// reflects the host, not the limits actually enforced on the process, so
+/// this is what should be shown when running under a cgroup.
+///
+/// All fields are stored in kB, matching [`MemoryStats`], even though the
+/// cgroup files themselves are in bytes.
+#[derive(Default, Clone, Copy, Serialize)]
+pub struct CgroupMemoryStats {
+    pub version: CgroupVersion,
+    pub limit: u64,
+    pub usage: u64,
+    pub cache: u64,
+    pub swap: u64,
+}
+
+impl CgroupMemoryStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ### Update
+    /// Detects cgroup v2 by the presence of `/sys/fs/cgroup/cgroup.controllers`
+    /// and falls back to cgroup v1 otherwise. `mem_total` is the host's
+    /// `MemTotal` (in kB, as read by [`MemoryStats::update`]) used as the
+    /// limit when the cgroup itself is unbounded.
+    pub fn update(&mut self, mem_total: u64) -> Result<(), AnyError> {
+        if Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+            self.update_v2(mem_total)
+        } else {
+            self.update_v1(mem_total)
+        }
+    }
+
+    fn update_v2(&mut self, mem_total: u64) -> Result<(), AnyError> {
+        self.version = CgroupVersion::V2;
+
+        self.usage = fs::read_to_string("/sys/fs/cgroup/memory.current")?
+            .trim()
+            .parse::<u64>()?
+            / 1024;
+
+        let max = fs::read_to_string("/sys/fs/cgroup/memory.max")?;
+        self.limit = parse_cgroup_v2_limit(&max, mem_total)?;
+
+        let stat = fs::read_to_string("/sys/fs/cgroup/memory.stat")?;
+        (self.cache, self.swap) = parse_cgroup_v2_stat(&stat)?;
+
+        Ok(())
+    }
+
+    fn update_v1(&mut self, mem_total: u64) -> Result<(), AnyError> {
+        self.version = CgroupVersion::V1;
+
+        self.usage = fs::read_to_string("/sys/fs/cgroup/memory/memory.usage_in_bytes")?
+            .trim()
+            .parse::<u64>()?
+            / 1024;
+
+        let limit = fs::read_to_string("/sys/fs/cgroup/memory/memory.limit_in_bytes")?;
+        self.limit = parse_cgroup_v1_limit(&limit, mem_total)?;
+
+        let stat = fs::read_to_string("/sys/fs/cgroup/memory/memory.stat")?;
+        (self.cache, self.swap) = parse_cgroup_v1_stat(&stat)?;
+
+        Ok(())
+    }
+
+    /// Displays container-scoped memory stats with the same 15-wide columns as
+    /// [`MemoryStats::display`]; the first heading is widened to sit over the
+    /// value that follows the longer `cgroup vN:` label.
+    pub fn display(&self) {
+        let fmt = |s: u64| format!("{:>15}", format_size(s));
+
+        let label = match self.version {
+            CgroupVersion::V1 => "cgroup v1",
+            CgroupVersion::V2 => "cgroup v2",
+            CgroupVersion::None => return,
+        };
+
         println!(
-            "{} {} {} {} {:>15} {} {}",
-            "Swap:".bold().purple(),
-            fmt_swap(self.swap_total).green(),
-            fmt(self.swap_used).red(),
-            fmt(self.swap_free).cyan(),
-            "",
-            fmt(self.swap_cached).yellow(),
-            fmt(self.swap_free).blue()
+            "\n{:>26} {:>15} {:>15} {:>15}",
+            "limit".bold(),
+            "usage".bold(),
+            "cache".bold(),
+            "swap".bold()
         );
         println!(
-            "{} {} {} {} {:>15} {:>15} {}",
-            "Total:".bold().blue(),
-            fmt_total(self.totalvmem).green(),
-            fmt(self.usedvmem).red(),
-            fmt(self.freevmem).cyan(),
-            "",
-            "",
-            fmt(self.availablevmem).blue()
+            "{} {} {} {} {}",
+            format!("{}:", label).bold().cyan(),
+            fmt(self.limit).green(),
+            fmt(self.usage).red(),
+            fmt(self.cache).magenta(),
+            fmt(self.swap).yellow(),
         );
-        println!(
-            "\n{:>17} {:>15} {:>15}",
-            "Zswap".bold(),
-            "Compressed".bold(),
-            "Ratio".bold()
+    }
+}
+
+/// Parses a cgroup v2 `memory.max` file's content into a limit in kB, falling
+/// back to `mem_total` when the cgroup itself is unbounded (the literal `max`).
+fn parse_cgroup_v2_limit(max: &str, mem_total: u64) -> Result<u64, AnyError> {
+    let max = max.trim();
+    if max == "max" {
+        Ok(mem_total)
+    } else {
+        Ok(max.parse::<u64>()? / 1024)
+    }
+}
+
+/// Parses a cgroup v2 `memory.stat` file's content into `(cache, swap)` kB,
+/// reading the `file` and `swap` fields.
+fn parse_cgroup_v2_stat(stat: &str) -> Result<(u64, u64), AnyError> {
+    let (mut cache, mut swap) = (0, 0);
+    for line in stat.lines() {
+        let mut split = line.split_whitespace();
+        let (key, value) = (
+            split.next().ok_or("bad file format")?,
+            split.next().ok_or("bad file format")?,
         );
-        println!(
-            "{} {} {} {}",
-            "Zswap:".bold().purple(),
-            fmt_zswap(self.zswap).green(),
-            fmt(self.zswap_compressed).red(),
-            fmt_ratio(self.compression_ratio).cyan()
+        match key {
+            "file" => cache = value.parse::<u64>()? / 1024,
+            "swap" => swap = value.parse::<u64>()? / 1024,
+            _ => (),
+        }
+    }
+
+    Ok((cache, swap))
+}
+
+/// Parses a cgroup v1 `memory.limit_in_bytes` file's content into a limit in
+/// kB, falling back to `mem_total` when the cgroup itself is unbounded (the
+/// sentinel [`CGROUP_V1_UNLIMITED`]).
+fn parse_cgroup_v1_limit(limit: &str, mem_total: u64) -> Result<u64, AnyError> {
+    let limit = limit.trim().parse::<u64>()?;
+    if limit >= CGROUP_V1_UNLIMITED {
+        Ok(mem_total)
+    } else {
+        Ok(limit / 1024)
+    }
+}
+
+/// Parses a cgroup v1 `memory.stat` file's content into `(cache, swap)` kB,
+/// reading the `cache` and `swap` fields.
+fn parse_cgroup_v1_stat(stat: &str) -> Result<(u64, u64), AnyError> {
+    let (mut cache, mut swap) = (0, 0);
+    for line in stat.lines() {
+        let mut split = line.split_whitespace();
+        let (key, value) = (
+            split.next().ok_or("bad file format")?,
+            split.next().ok_or("bad file format")?,
         );
+        match key {
+            "cache" => cache = value.parse::<u64>()? / 1024,
+            "swap" => swap = value.parse::<u64>()? / 1024,
+            _ => (),
+        }
     }
+
+    Ok((cache, swap))
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize)]
 pub struct ProcessMemoryStats {
     pub pid: u32,
     pub username: String,
@@ -161,19 +541,24 @@ impl ProcessMemoryStats {
         Self::default()
     }
 
-    /// Update the process memory stats
+    /// Update the process memory stats. `usernames` is a UID → name lookup,
+    /// built once by [`build_username_map`], so callers updating many
+    /// processes (e.g. [`collect_processes`]) don't re-read `/etc/passwd`
+    /// per PID.
     /// # Examples
     /// ```
     /// let mut pms = ProcessMemoryStats::new();
-    /// pms.update(1)?;
+    /// let usernames = build_username_map()?;
+    /// pms.update(&1, &usernames)?;
     /// ```
-    pub fn update(&mut self, pid: &u32) -> Result<(), AnyError> {
+    pub fn update(&mut self, pid: &u32, usernames: &HashMap<u32, String>) -> Result<(), AnyError> {
         self.command = get_cmd(*pid)?;
         if self.command.len() > 50 {
             self.command.truncate(50);
         }
 
         self.pid = *pid;
+        self.username = resolve_username(fs::metadata(format!("/proc/{}", pid))?.uid(), usernames);
 
         // This is the sum of all the smaps data but it is much more performant to get it this way.
         // Since 4.14 and requires CONFIG_PROC_PAGE_MONITOR
@@ -206,17 +591,387 @@ impl ProcessMemoryStats {
         Ok(())
     }
 
-    pub fn display(&self) {
+    /// `total_mem` is the host's `MemTotal` (kB), used to judge how much
+    /// pressure this single process's share of memory represents.
+    pub fn display(&self, total_mem: u64, thresholds: &MemoryThresholds) {
         let fmt = |s: String| format!("{:>14}", s);
+        let pss_pct = if total_mem > 0 {
+            self.pss as f64 / total_mem as f64 * 100.0
+        } else {
+            0.0
+        };
+        let pss_color = pressure_color(pss_pct, thresholds);
 
         println!(
             "{:>10} {} {} {} {} {}",
             self.pid,
             fmt(format_size(self.swap)).red(),
             fmt(format_size(self.uss)).green(),
-            fmt(format_size(self.pss)).blue(),
+            fmt(format_size(self.pss)).color(pss_color),
             fmt(format_size(self.rss)).cyan(),
             self.command
         );
     }
 }
+
+/// Which field drives descending sort order in the process table.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Pss,
+    Uss,
+    Rss,
+    Swap,
+}
+
+impl SortKey {
+    fn value(self, p: &ProcessMemoryStats) -> u64 {
+        match self {
+            SortKey::Pss => p.pss,
+            SortKey::Uss => p.uss,
+            SortKey::Rss => p.rss,
+            SortKey::Swap => p.swap,
+        }
+    }
+}
+
+/// Options controlling which processes [`collect_processes`] returns and in
+/// what order. PSS is the default sort key since it's the fairest accounting
+/// of memory shared between processes.
+#[derive(Clone, Copy, Default)]
+pub struct ProcessListOptions {
+    pub sort_key: SortKey,
+    pub top: Option<usize>,
+    pub min_size: u64,
+}
+
+/// ### Collect Processes
+/// Walks `/proc`, builds a [`ProcessMemoryStats`] for every numeric PID, drops
+/// entries below `options.min_size` (measured by `options.sort_key`), sorts
+/// descending by that same key, and truncates to `options.top` if set.
+pub fn collect_processes(
+    options: &ProcessListOptions,
+) -> Result<Vec<ProcessMemoryStats>, AnyError> {
+    let usernames = build_username_map()?;
+    let mut processes = Vec::new();
+
+    for entry in fs::read_dir("/proc")? {
+        let entry = entry?;
+        let pid: u32 = match entry.file_name().to_string_lossy().parse() {
+            Ok(pid) => pid,
+            Err(_) => continue,
+        };
+
+        let mut pms = ProcessMemoryStats::new();
+        // Processes can exit between the readdir and the smaps_rollup read,
+        // or be owned by another user we can't inspect; skip those.
+        if pms.update(&pid, &usernames).is_err() {
+            continue;
+        }
+
+        if options.sort_key.value(&pms) < options.min_size {
+            continue;
+        }
+
+        processes.push(pms);
+    }
+
+    processes.sort_by_key(|p| Reverse(options.sort_key.value(p)));
+
+    if let Some(top) = options.top {
+        processes.truncate(top);
+    }
+
+    Ok(processes)
+}
+
+/// ### Build Username Map
+/// Scans `/etc/passwd` once into a UID → name lookup. Callers resolving many
+/// PIDs (e.g. [`collect_processes`]) should build this once up front instead
+/// of re-reading the file per process.
+pub fn build_username_map() -> Result<HashMap<u32, String>, AnyError> {
+    let mut usernames = HashMap::new();
+
+    let contents = fs::read_to_string("/etc/passwd")?;
+    for line in contents.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        if let (Some(name), Some(Ok(uid))) = (fields.first(), fields.get(2).map(|uid| uid.parse()))
+        {
+            usernames.insert(uid, name.to_string());
+        }
+    }
+
+    Ok(usernames)
+}
+
+/// Resolves a UID to a username from a map built by [`build_username_map`],
+/// falling back to the bare UID when it isn't listed (e.g. a deleted user,
+/// or a uid mapped only inside a container namespace).
+fn resolve_username(uid: u32, usernames: &HashMap<u32, String>) -> String {
+    usernames
+        .get(&uid)
+        .cloned()
+        .unwrap_or_else(|| uid.to_string())
+}
+
+/// Per-user roll-up of process memory, answering "which user is eating all the RAM?".
+#[derive(Default, Clone, Serialize)]
+pub struct UserMemoryStats {
+    pub username: String,
+    pub swap: u64,
+    pub uss: u64,
+    pub pss: u64,
+    pub rss: u64,
+}
+
+impl UserMemoryStats {
+    /// `total_mem` is the host's `MemTotal` (kB), used to judge how much
+    /// pressure this user's share of memory represents.
+    pub fn display(&self, total_mem: u64, thresholds: &MemoryThresholds) {
+        let fmt = |s: String| format!("{:>14}", s);
+        let pss_pct = if total_mem > 0 {
+            self.pss as f64 / total_mem as f64 * 100.0
+        } else {
+            0.0
+        };
+        let pss_color = pressure_color(pss_pct, thresholds);
+
+        println!(
+            "{:>10} {} {} {} {}",
+            self.username,
+            fmt(format_size(self.swap)).red(),
+            fmt(format_size(self.uss)).green(),
+            fmt(format_size(self.pss)).color(pss_color),
+            fmt(format_size(self.rss)).cyan(),
+        );
+    }
+}
+
+/// ### Aggregate By User
+/// Folds a process list (as returned by [`collect_processes`]) into one row
+/// per user, summing PSS/USS/RSS/SWAP, sorted descending by PSS.
+pub fn aggregate_by_user(processes: &[ProcessMemoryStats]) -> Vec<UserMemoryStats> {
+    let mut by_user: HashMap<String, UserMemoryStats> = HashMap::new();
+
+    for p in processes {
+        let entry = by_user
+            .entry(p.username.clone())
+            .or_insert_with(|| UserMemoryStats {
+                username: p.username.clone(),
+                ..Default::default()
+            });
+        entry.swap += p.swap;
+        entry.uss += p.uss;
+        entry.pss += p.pss;
+        entry.rss += p.rss;
+    }
+
+    let mut users: Vec<UserMemoryStats> = by_user.into_values().collect();
+    users.sort_by_key(|u| Reverse(u.pss));
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cgroup_v2_limit_falls_back_to_mem_total_when_unlimited() {
+        assert_eq!(
+            parse_cgroup_v2_limit("max\n", 16_000_000).unwrap(),
+            16_000_000
+        );
+    }
+
+    #[test]
+    fn parse_cgroup_v2_limit_converts_bytes_to_kb() {
+        assert_eq!(
+            parse_cgroup_v2_limit("1048576\n", 16_000_000).unwrap(),
+            1024
+        );
+    }
+
+    #[test]
+    fn parse_cgroup_v2_stat_reads_file_and_swap() {
+        let stat = "anon 100\nfile 2097152\nswap 1048576\n";
+        assert_eq!(parse_cgroup_v2_stat(stat).unwrap(), (2048, 1024));
+    }
+
+    #[test]
+    fn parse_cgroup_v1_limit_falls_back_to_mem_total_when_unlimited() {
+        let limit = format!("{}\n", CGROUP_V1_UNLIMITED);
+        assert_eq!(
+            parse_cgroup_v1_limit(&limit, 16_000_000).unwrap(),
+            16_000_000
+        );
+    }
+
+    #[test]
+    fn parse_cgroup_v1_limit_converts_bytes_to_kb() {
+        assert_eq!(
+            parse_cgroup_v1_limit("1048576\n", 16_000_000).unwrap(),
+            1024
+        );
+    }
+
+    #[test]
+    fn parse_cgroup_v1_stat_reads_cache_and_swap() {
+        let stat = "cache 2097152\nrss 100\nswap 1048576\n";
+        assert_eq!(parse_cgroup_v1_stat(stat).unwrap(), (2048, 1024));
+    }
+
+    #[test]
+    fn sort_key_value_reads_the_matching_field() {
+        let p = ProcessMemoryStats {
+            pss: 1,
+            uss: 2,
+            rss: 3,
+            swap: 4,
+            ..Default::default()
+        };
+
+        assert_eq!(SortKey::Pss.value(&p), 1);
+        assert_eq!(SortKey::Uss.value(&p), 2);
+        assert_eq!(SortKey::Rss.value(&p), 3);
+        assert_eq!(SortKey::Swap.value(&p), 4);
+    }
+
+    #[test]
+    fn sort_key_orders_descending_by_chosen_key() {
+        let mut processes = [
+            ProcessMemoryStats {
+                pid: 1,
+                uss: 10,
+                ..Default::default()
+            },
+            ProcessMemoryStats {
+                pid: 2,
+                uss: 30,
+                ..Default::default()
+            },
+            ProcessMemoryStats {
+                pid: 3,
+                uss: 20,
+                ..Default::default()
+            },
+        ];
+
+        processes.sort_by_key(|p| Reverse(SortKey::Uss.value(p)));
+
+        assert_eq!(
+            processes.iter().map(|p| p.pid).collect::<Vec<_>>(),
+            [2, 3, 1]
+        );
+    }
+
+    #[test]
+    fn pressure_color_picks_green_yellow_red_by_threshold() {
+        let thresholds = MemoryThresholds {
+            warning_pct: 80.0,
+            critical_pct: 90.0,
+        };
+
+        assert_eq!(pressure_color(50.0, &thresholds), Color::Green);
+        assert_eq!(pressure_color(80.0, &thresholds), Color::Yellow);
+        assert_eq!(pressure_color(89.9, &thresholds), Color::Yellow);
+        assert_eq!(pressure_color(90.0, &thresholds), Color::Red);
+        assert_eq!(pressure_color(100.0, &thresholds), Color::Red);
+    }
+
+    #[test]
+    fn resolve_username_looks_up_known_uid() {
+        let mut usernames = HashMap::new();
+        usernames.insert(1000, "alice".to_string());
+
+        assert_eq!(resolve_username(1000, &usernames), "alice");
+    }
+
+    #[test]
+    fn resolve_username_falls_back_to_uid() {
+        let usernames = HashMap::new();
+
+        assert_eq!(resolve_username(1000, &usernames), "1000");
+    }
+
+    #[test]
+    fn aggregate_by_user_sums_and_sorts_descending() {
+        let processes = vec![
+            ProcessMemoryStats {
+                username: "alice".to_string(),
+                pss: 100,
+                ..Default::default()
+            },
+            ProcessMemoryStats {
+                username: "bob".to_string(),
+                pss: 500,
+                ..Default::default()
+            },
+            ProcessMemoryStats {
+                username: "alice".to_string(),
+                pss: 50,
+                ..Default::default()
+            },
+        ];
+
+        let users = aggregate_by_user(&processes);
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].username, "bob");
+        assert_eq!(users[0].pss, 500);
+        assert_eq!(users[1].username, "alice");
+        assert_eq!(users[1].pss, 150);
+    }
+
+    #[test]
+    fn format_rows_emits_one_csv_row_per_element() {
+        let processes = vec![
+            ProcessMemoryStats {
+                pid: 1,
+                pss: 100,
+                ..Default::default()
+            },
+            ProcessMemoryStats {
+                pid: 2,
+                pss: 200,
+                ..Default::default()
+            },
+            ProcessMemoryStats {
+                pid: 3,
+                pss: 50,
+                ..Default::default()
+            },
+        ];
+
+        let csv = format_rows(&processes, OutputFormat::Csv).unwrap();
+        // header + one row per element, not everything flattened onto one line.
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.lines().nth(1).unwrap().contains("1,"));
+        assert!(csv.lines().nth(2).unwrap().contains("2,"));
+        assert!(csv.lines().nth(3).unwrap().contains("3,"));
+    }
+
+    #[test]
+    fn format_rows_json_is_an_array() {
+        let users = vec![UserMemoryStats {
+            username: "alice".to_string(),
+            pss: 100,
+            ..Default::default()
+        }];
+
+        let json = format_rows(&users, OutputFormat::Json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(parsed.is_array());
+        assert_eq!(parsed.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn format_output_serializes_a_single_record() {
+        let stats = MemoryStats {
+            total: 16_000_000,
+            ..Default::default()
+        };
+
+        let csv = format_output(&stats, OutputFormat::Csv).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+    }
+}